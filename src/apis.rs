@@ -1,121 +1,300 @@
-use std::{fs, thread, time::Duration};
+use std::{thread, time::Duration};
 
-use axum::{routing::post, Json, Router};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
-use crate::sandbox::Sandbox;
+use crate::constants::Settings;
+use crate::runners::{LanguageRunner, Workdir};
+use crate::sandbox::{ExecutionOutcome, Sandbox};
+use crate::session::{IoDisposition, PtySession};
+
+/// Tags prefixed onto each forwarded session frame so the client can tell which stream
+/// a chunk came from even though both are multiplexed onto the same WebSocket.
+const STDOUT_TAG: u8 = 0;
+const STDERR_TAG: u8 = 1;
 
 #[derive(Deserialize)]
 pub struct ExecutionRequest {
     code: String,
+    /// Whether stdin is fed to the process at all; batch endpoints never forward bytes
+    /// into it, but `Null` lets callers signal "don't wait on stdin" for programs that
+    /// would otherwise block reading it.
+    #[serde(default)]
+    stdin: IoDisposition,
+    /// For `/run/:lang`, stdout is always captured and returned separately from stderr
+    /// regardless of this field; `Null` has no effect there. For `/session/:lang`, this
+    /// controls whether stdout frames are forwarded over the WebSocket at all — it's
+    /// independent of `stderr`, since each stream is captured on its own channel (see
+    /// `STDOUT_TAG`/`STDERR_TAG`).
+    #[serde(default)]
+    stdout: IoDisposition,
+    /// Same as `stdout`, but for the stderr stream; set one to `Null` and leave the other
+    /// `Piped` to receive only one of the two.
+    #[serde(default)]
+    stderr: IoDisposition,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, Default)]
 pub struct ExecutionResponse {
-    output: String,
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    timed_out: bool,
+    wall_time_ms: u128,
+    cpu_time_ms: Option<u64>,
 }
 
-pub async fn run_python(Json(payload): Json<ExecutionRequest>) -> Json<ExecutionResponse> {
-    if let Some(sandbox) = Sandbox::new() {
-        let timeout = 15;
-        let start_time = std::time::Instant::now();
+impl ExecutionResponse {
+    fn failed(message: String) -> Self {
+        Self {
+            stderr: message,
+            ..Self::default()
+        }
+    }
 
-        let filename = format!("/tmp/{}.py", Uuid::new_v4());
+    fn timed_out(wall_time_ms: u128) -> Self {
+        Self {
+            stderr: "Execution timed out".to_string(),
+            timed_out: true,
+            wall_time_ms,
+            ..Self::default()
+        }
+    }
 
-        // TODO: delete this file after execution
-        if let Err(e) = fs::write(&filename, &payload.code) {
-            return Json(ExecutionResponse {
-                output: format!("Failed to write code to file: {}", e),
-            });
+    fn from_outcome(outcome: ExecutionOutcome, cpu_time_ms: Option<u64>) -> Self {
+        Self {
+            stdout: outcome.stdout,
+            stderr: outcome.stderr,
+            exit_code: outcome.exit_code,
+            signal: outcome.signal,
+            timed_out: false,
+            wall_time_ms: outcome.wall_time_ms,
+            cpu_time_ms,
         }
+    }
+}
 
-        let command = format!("python3 {}", filename);
+/// Runs `payload.code` for `lang` by looking it up in `runners::REGISTRY` and executing
+/// the shared write -> (compile) -> run pipeline. Adding a language is a new registry
+/// entry, not a new handler.
+pub async fn run_language(
+    Path(lang): Path<String>,
+    Json(payload): Json<ExecutionRequest>,
+) -> Json<ExecutionResponse> {
+    let Some(runner) = LanguageRunner::lookup(&lang) else {
+        return Json(ExecutionResponse::failed(format!(
+            "Unsupported language: {}",
+            lang
+        )));
+    };
 
-        while start_time.elapsed().as_secs() < timeout {
-            if sandbox.is_running() {
-                match sandbox.run_command(&command) {
-                    Ok(output) => {
-                        sandbox.terminate();
-                        return Json(ExecutionResponse { output });
-                    }
-                    Err(output) => {
-                        sandbox.terminate();
-                        return Json(ExecutionResponse { output });
-                    }
-                }
+    let settings = Settings::from_env();
+    let Some(sandbox) = Sandbox::new(settings.resource_limits()) else {
+        return Json(ExecutionResponse::failed("Failed to create sandbox".to_string()));
+    };
+
+    let workdir = match runner.write_source(&payload.code) {
+        Ok(workdir) => workdir,
+        Err(e) => {
+            sandbox.terminate();
+            return Json(ExecutionResponse::failed(format!(
+                "Failed to write code to file: {}",
+                e
+            )));
+        }
+    };
+
+    if let Some(compile_command) = runner.compile_command(&workdir) {
+        match sandbox.run_command("compiler", &compile_command) {
+            Ok(outcome) if outcome.exit_code == Some(0) => {}
+            Ok(outcome) => {
+                sandbox.terminate();
+                return Json(ExecutionResponse::failed(format!(
+                    "Compilation failed:\n{}",
+                    outcome.stderr
+                )));
+            }
+            Err(e) => {
+                sandbox.terminate();
+                return Json(ExecutionResponse::failed(e));
             }
-            thread::sleep(Duration::from_secs(1));
         }
+    }
 
-        sandbox.terminate();
-        return Json(ExecutionResponse {
-            output: "Execution timed out".to_string(),
-        });
+    let mut run_command = runner.run_command(&workdir);
+    if payload.stdin == IoDisposition::Null {
+        run_command.push_str(" < /dev/null");
     }
 
-    Json(ExecutionResponse {
-        output: "Failed to create sandbox".to_string(),
-    })
+    let start_time = std::time::Instant::now();
+    while start_time.elapsed().as_secs() < settings.wall_timeout_secs {
+        if sandbox.is_running() {
+            let response = match sandbox.run_command("interpreter", &run_command) {
+                Ok(outcome) => ExecutionResponse::from_outcome(outcome, sandbox.cpu_time_ms()),
+                Err(e) => ExecutionResponse::failed(e),
+            };
+            sandbox.terminate();
+            return Json(response);
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    sandbox.terminate();
+    Json(ExecutionResponse::timed_out(start_time.elapsed().as_millis()))
 }
 
-pub async fn run_cpp(Json(payload): Json<ExecutionRequest>) -> Json<ExecutionResponse> {
-    if let Some(sandbox) = Sandbox::new() {
-        let timeout = 15;
-        let start_time = std::time::Instant::now();
+/// Upgrades to a WebSocket and attaches an interactive, PTY-backed session for `lang`.
+/// The first message on the socket must be a JSON-encoded `ExecutionRequest`; every
+/// message after that is raw bytes forwarded to/from the PTY.
+pub async fn session_handler(Path(lang): Path<String>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_session(socket, lang))
+}
+
+async fn handle_session(mut socket: WebSocket, lang: String) {
+    let Some(Ok(Message::Text(init_text))) = socket.next().await else {
+        return;
+    };
+    let Ok(payload) = serde_json::from_str::<ExecutionRequest>(&init_text) else {
+        let _ = socket
+            .send(Message::Text("Invalid session init payload".to_string()))
+            .await;
+        return;
+    };
 
-        let id = Uuid::new_v4();
-        let source_file = format!("/tmp/{}.cpp", id);
-        let binary_file = format!("/tmp/{}", id);
+    let Some(sandbox) = Sandbox::new(Settings::from_env().resource_limits()) else {
+        let _ = socket
+            .send(Message::Text("Failed to create sandbox".to_string()))
+            .await;
+        return;
+    };
 
-        // TODO: delete this file after execution
-        if let Err(e) = fs::write(&source_file, &payload.code) {
-            return Json(ExecutionResponse {
-                output: format!("Failed to write code to file: {}", e),
-            });
+    // `_workdir` is held until the session ends so its temp files survive as long as the
+    // interactive process needs them; it cleans itself up on drop.
+    let (command, _workdir) = match prepare_session_command(&sandbox, &lang, &payload.code) {
+        Ok(pair) => pair,
+        Err(e) => {
+            let _ = socket.send(Message::Text(e)).await;
+            sandbox.terminate();
+            return;
         }
+    };
 
-        let compile_command = format!("g++ -o {} {}", binary_file, source_file);
-        match sandbox.run_command(&compile_command) {
-            Ok(_) => {
-                let run_command = format!("{}", binary_file);
-                while start_time.elapsed().as_secs() < timeout {
-                    if sandbox.is_running() {
-                        match sandbox.run_command(&run_command) {
-                            Ok(output) => {
-                                sandbox.terminate();
-                                return Json(ExecutionResponse { output });
-                            }
-                            Err(output) => {
-                                sandbox.terminate();
-                                return Json(ExecutionResponse { output });
-                            }
-                        }
+    let (session, mut child) = match sandbox.run_interactive("interpreter", &command) {
+        Ok(pair) => pair,
+        Err(e) => {
+            let _ = socket
+                .send(Message::Text(format!("Failed to start interactive session: {}", e)))
+                .await;
+            sandbox.terminate();
+            return;
+        }
+    };
+
+    let session = std::sync::Arc::new(session);
+    let (mut ws_sink, mut ws_stream) = socket.split();
+
+    // stdout and stderr are captured on independent channels (see `PtySession`), so each
+    // can be forwarded or suppressed on its own; a single byte tags which stream a frame
+    // came from (`STDOUT_TAG`/`STDERR_TAG`).
+    let (output_tx, mut output_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(32);
+    let stdout_reader_task = (payload.stdout != IoDisposition::Null)
+        .then(|| spawn_stream_reader(session.clone(), STDOUT_TAG, output_tx.clone(), PtySession::read_output));
+    let stderr_reader_task = (payload.stderr != IoDisposition::Null)
+        .then(|| spawn_stream_reader(session.clone(), STDERR_TAG, output_tx.clone(), PtySession::read_stderr));
+    drop(output_tx);
+
+    let forward_task = tokio::spawn(async move {
+        while let Some(chunk) = output_rx.recv().await {
+            if ws_sink.send(Message::Binary(chunk)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let stdin_open = payload.stdin != IoDisposition::Null;
+    while let Some(Ok(msg)) = ws_stream.next().await {
+        match msg {
+            Message::Binary(bytes) if stdin_open => {
+                let _ = session.write_input(&bytes);
+            }
+            Message::Text(text) if stdin_open => {
+                let _ = session.write_input(text.as_bytes());
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    let _ = child.kill();
+    sandbox.terminate();
+    if let Some(task) = stdout_reader_task {
+        task.abort();
+    }
+    if let Some(task) = stderr_reader_task {
+        task.abort();
+    }
+    forward_task.abort();
+}
+
+/// Spawns a blocking reader loop over one of a `PtySession`'s streams (`read_output` or
+/// `read_stderr`), tagging each chunk with `tag` and sending it to `tx` for the forwarding
+/// task to deliver over the WebSocket. Exits once the stream closes (`Ok(0)`), errors, or
+/// `tx`'s receiver is dropped.
+fn spawn_stream_reader(
+    session: std::sync::Arc<PtySession>,
+    tag: u8,
+    tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    read: fn(&PtySession, &mut [u8]) -> nix::Result<usize>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            match read(&session, &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let mut chunk = Vec::with_capacity(n + 1);
+                    chunk.push(tag);
+                    chunk.extend_from_slice(&buf[..n]);
+                    if tx.blocking_send(chunk).is_err() {
+                        break;
                     }
-                    thread::sleep(Duration::from_secs(1));
                 }
             }
-            Err(output) => {
-                sandbox.terminate();
-                return Json(ExecutionResponse {
-                    output: format!("Compilation failed:\n{}", output),
-                });
-            }
         }
+    })
+}
+
+/// Looks `lang` up in the registry, writes/compiles its source, and returns the command
+/// to run interactively along with the workdir backing it. Mirrors the
+/// write -> (compile) -> run pipeline in `run_language`.
+fn prepare_session_command(sandbox: &Sandbox, lang: &str, code: &str) -> Result<(String, Workdir), String> {
+    let runner =
+        LanguageRunner::lookup(lang).ok_or_else(|| format!("Unsupported session language: {}", lang))?;
+
+    let workdir = runner
+        .write_source(code)
+        .map_err(|e| format!("Failed to write code to file: {}", e))?;
 
-        sandbox.terminate();
-        Json(ExecutionResponse {
-            output: "Execution timed out".to_string(),
-        })
-    } else {
-        Json(ExecutionResponse {
-            output: "Failed to create sandbox".to_string(),
-        })
+    if let Some(compile_command) = runner.compile_command(&workdir) {
+        let outcome = sandbox.run_command("compiler", &compile_command)?;
+        if outcome.exit_code != Some(0) {
+            return Err(format!("Compilation failed:\n{}", outcome.stderr));
+        }
     }
+
+    Ok((runner.run_command(&workdir), workdir))
 }
 
 pub fn get_routes() -> Router {
     Router::new()
-        .route("/python", post(run_python))
-        .route("/cpp", post(run_cpp))
+        .route("/run/:lang", post(run_language))
+        .route("/session/:lang", get(session_handler))
 }
@@ -0,0 +1,58 @@
+//! Helper binary exec'd (via `nsenter --no-fork`) in place of a bare `sh -c` once a
+//! sandbox's namespaces have been joined. Installing the seccomp filter and rlimits here,
+//! immediately before exec'ing the real command, is what actually lands them on the
+//! workload: a filter or rlimit applied to the holder process in `Sandbox::new` is never
+//! inherited across `setns`, only across `fork`/`exec`.
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use nix::libc::{rlimit, setrlimit, RLIMIT_CPU, RLIMIT_FSIZE};
+
+use rusty_sandbox::constants::Settings;
+use rusty_sandbox::seccomp_policy::SeccompPolicy;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, profile, cpu_seconds, max_file_bytes, command] = args.as_slice() else {
+        eprintln!("Usage: sandbox_exec <seccomp-profile> <cpu-seconds> <max-file-bytes> <command>");
+        std::process::exit(1);
+    };
+
+    set_rlimit(RLIMIT_CPU, parse_limit(cpu_seconds));
+    set_rlimit(RLIMIT_FSIZE, parse_limit(max_file_bytes));
+
+    let policy = match Settings::from_env().seccomp_policy_path {
+        Some(path) => SeccompPolicy::from_file(Path::new(&path)),
+        None => SeccompPolicy::named(profile),
+    };
+    policy.install();
+
+    let err = Command::new("sh").arg("-c").arg(command).exec();
+    eprintln!("Failed to exec sandboxed command: {}", err);
+    std::process::exit(127);
+}
+
+fn parse_limit(value: &str) -> u64 {
+    value
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid rlimit value '{}': {}", value, e))
+}
+
+/// Sets `resource` on the calling (this) process. Rlimits are inherited across
+/// `fork`/`exec`, so setting them here immediately before the final `exec` correctly
+/// bounds the command it execs into.
+fn set_rlimit(resource: u32, limit: u64) {
+    let rlim = rlimit {
+        rlim_cur: limit,
+        rlim_max: limit,
+    };
+
+    if unsafe { setrlimit(resource, &rlim) } != 0 {
+        eprintln!(
+            "Failed to set rlimit {}: {}",
+            resource,
+            std::io::Error::last_os_error()
+        );
+    }
+}
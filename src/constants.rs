@@ -1,13 +1,52 @@
 use std::env;
+use std::str::FromStr;
 
 use dotenv::dotenv;
 
+/// Resource caps applied to a single sandboxed process. Threaded into `Sandbox::new` so
+/// every request can be created with explicit limits instead of hardcoded values.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceLimits {
+    pub cpu_seconds: u64,
+    pub memory_bytes: u64,
+    pub max_processes: u32,
+    pub max_file_bytes: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Settings {
     pub app_host: String,
     pub use_complete_isolation: bool,
+    /// Path to a declarative seccomp profile (see `seccomp_policy::SeccompPolicy::from_file`).
+    /// When unset, callers fall back to the built-in "strict"/"interpreter"/"compiler" profiles by name.
+    pub seccomp_policy_path: Option<String>,
+    /// `RLIMIT_CPU`, in seconds.
+    pub cpu_seconds: u64,
+    /// cgroup `memory.max` (or `memory.limit_in_bytes` on v1), in bytes.
+    pub memory_bytes: u64,
+    /// cgroup `pids.max`.
+    pub max_processes: u32,
+    /// `RLIMIT_FSIZE`, in bytes.
+    pub max_file_bytes: u64,
+    /// Path to the read-only base rootfs used as the overlay's lower layer (see
+    /// `rootfs::SandboxRoot`). Must contain `/bin`, `/lib`, `python3`, `g++`, etc.
+    pub base_rootfs_path: String,
+    /// How long `run_language`'s poll loop waits for the sandboxed process to come up
+    /// before giving up, in seconds.
+    pub wall_timeout_secs: u64,
+    /// Host filesystem path to the compiled `sandbox_exec` helper binary (see
+    /// `src/bin/sandbox_exec.rs`). Bind-mounted into each sandbox's root at
+    /// `SANDBOXED_EXEC_PATH` so `nsenter` can exec it from inside the joined mount
+    /// namespace, which is the only place a seccomp filter or rlimit can actually land on
+    /// the real workload rather than the sleeping holder.
+    pub sandbox_exec_path: String,
 }
 
+/// Fixed path `sandbox_exec` is bind-mounted to inside a sandbox's root by
+/// `rootfs::SandboxRoot::enter`, and the path `Sandbox::run_command`/`run_interactive`
+/// point `nsenter` at once they've joined that mount namespace.
+pub const SANDBOXED_EXEC_PATH: &str = "/sandbox_exec";
+
 impl Settings {
     pub fn from_env() -> Self {
         dotenv().ok();
@@ -17,10 +56,45 @@ impl Settings {
             .unwrap_or_else(|_| "false".to_string())
             .parse::<bool>()
             .unwrap_or(false);
+        let seccomp_policy_path = env::var("SECCOMP_POLICY_PATH").ok();
+        let cpu_seconds = env_parsed("CPU_SECONDS", 10);
+        let memory_bytes = env_parsed("MEMORY_BYTES", 128 * 1024 * 1024);
+        let max_processes = env_parsed("MAX_PROCESSES", 20);
+        let max_file_bytes = env_parsed("MAX_FILE_BYTES", 20 * 1024 * 1024);
+        let base_rootfs_path =
+            env::var("BASE_ROOTFS_PATH").unwrap_or_else(|_| "/opt/sandbox-rootfs".to_string());
+        let wall_timeout_secs = env_parsed("WALL_TIMEOUT_SECS", 15);
+        let sandbox_exec_path = env::var("SANDBOX_EXEC_PATH")
+            .unwrap_or_else(|_| "/usr/local/bin/sandbox_exec".to_string());
 
         return Self {
             app_host,
             use_complete_isolation,
+            seccomp_policy_path,
+            cpu_seconds,
+            memory_bytes,
+            max_processes,
+            max_file_bytes,
+            base_rootfs_path,
+            wall_timeout_secs,
+            sandbox_exec_path,
         };
     }
+
+    pub fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            cpu_seconds: self.cpu_seconds,
+            memory_bytes: self.memory_bytes,
+            max_processes: self.max_processes,
+            max_file_bytes: self.max_file_bytes,
+        }
+    }
+}
+
+/// Parses an environment variable, falling back to `default` if it's unset or invalid.
+fn env_parsed<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
 }
@@ -0,0 +1,9 @@
+pub mod apis;
+pub mod app;
+pub mod cgroup;
+pub mod constants;
+pub mod rootfs;
+pub mod runners;
+pub mod sandbox;
+pub mod seccomp_policy;
+pub mod session;
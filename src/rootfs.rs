@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
+use nix::sys::stat::{makedev, mknod, Mode, SFlag};
+use nix::unistd::{chdir, pivot_root};
+
+use crate::constants::SANDBOXED_EXEC_PATH;
+
+/// Builds and enters a real root filesystem for a sandboxed process: a read-only base
+/// rootfs overlaid with a per-sandbox writable layer, plus a fresh `/proc`, a minimal
+/// `/dev`, and a size-limited `/tmp`. Replaces `chroot`, which a process that keeps an
+/// open directory fd (or that can call `chroot` again) is well known to be able to
+/// escape, and which never populated the root with anything `python3`/`g++` need.
+pub struct SandboxRoot;
+
+impl SandboxRoot {
+    /// Lays out the overlay and bind mounts under `sandbox/<pid>/` and pivots into it.
+    /// Must run after `CLONE_NEWNS` (a private mount namespace) and before dropping
+    /// privileges, since mounting requires them. `sandbox_exec_path` is the host path to
+    /// the compiled `sandbox_exec` helper, bind-mounted into the overlay so `nsenter` can
+    /// exec it once it has joined this sandbox's (now pivoted) mount namespace.
+    pub fn enter(pid: i32, base_rootfs: &str, sandbox_exec_path: &str) {
+        let sandbox_dir = PathBuf::from(format!("sandbox/{}", pid));
+        let lower = PathBuf::from(base_rootfs);
+        let upper = sandbox_dir.join("upper");
+        let work = sandbox_dir.join("work");
+        let merged = sandbox_dir.join("merged");
+
+        for dir in [&upper, &work, &merged] {
+            fs::create_dir_all(dir).expect("Failed to create overlay directory");
+        }
+
+        Self::remount_root_private();
+        Self::mount_overlay(&lower, &upper, &work, &merged);
+        Self::mount_proc(&merged);
+        Self::mount_dev(&merged);
+        Self::mount_tmp(&merged);
+        Self::mount_sandbox_exec(&merged, sandbox_exec_path);
+        Self::pivot(&merged);
+    }
+
+    /// Removes the `sandbox/<pid>/` tree (`upper`, `work`, `merged`, and everything
+    /// mounted under `merged`) from the host side. Safe to call once the holder and any
+    /// `nsenter`'d processes that joined its mount namespace have exited: the overlay,
+    /// `/proc`, `/dev`, `/tmp`, and `sandbox_exec` bind mounts created in `enter` live in
+    /// that private mount namespace and are torn down by the kernel when its last process
+    /// exits, leaving `merged` (and its subdirectories) as plain, removable directories
+    /// again. Without this, not only do these directories accumulate forever, but a reused
+    /// pid would find a stale `upper` layer from a previous, unrelated sandbox still
+    /// sitting there.
+    pub fn cleanup(pid: i32) {
+        let sandbox_dir = format!("sandbox/{}", pid);
+        if let Err(e) = fs::remove_dir_all(&sandbox_dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to clean up sandbox dir {}: {}", sandbox_dir, e);
+            }
+        }
+    }
+
+    /// `MS_REC | MS_PRIVATE` so mount/unmount events below this point never propagate
+    /// back out to the host's mount namespace.
+    fn remount_root_private() {
+        mount(
+            None::<&str>,
+            "/",
+            None::<&str>,
+            MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+            None::<&str>,
+        )
+        .expect("Failed to remount / as private");
+    }
+
+    /// Mounts an overlay with the base rootfs as its read-only lower layer and a
+    /// per-sandbox writable upper layer, so writes never touch the shared base image.
+    fn mount_overlay(lower: &Path, upper: &Path, work: &Path, merged: &Path) {
+        let options = format!(
+            "lowerdir={},upperdir={},workdir={}",
+            lower.display(),
+            upper.display(),
+            work.display()
+        );
+        mount(
+            Some("overlay"),
+            merged,
+            Some("overlay"),
+            MsFlags::empty(),
+            Some(options.as_str()),
+        )
+        .expect("Failed to mount overlay root");
+    }
+
+    fn mount_proc(merged: &Path) {
+        let proc_dir = merged.join("proc");
+        fs::create_dir_all(&proc_dir).expect("Failed to create /proc mount point");
+        mount(
+            Some("proc"),
+            &proc_dir,
+            Some("proc"),
+            MsFlags::empty(),
+            None::<&str>,
+        )
+        .expect("Failed to mount /proc");
+    }
+
+    /// A minimal `tmpfs` `/dev` with just the device nodes a Python/C++ process needs
+    /// (`null`, `zero`, `random`, `urandom`) rather than bind-mounting the host's `/dev`.
+    fn mount_dev(merged: &Path) {
+        let dev_dir = merged.join("dev");
+        fs::create_dir_all(&dev_dir).expect("Failed to create /dev mount point");
+        mount(
+            Some("tmpfs"),
+            &dev_dir,
+            Some("tmpfs"),
+            MsFlags::empty(),
+            Some("size=1m,mode=755"),
+        )
+        .expect("Failed to mount /dev tmpfs");
+
+        let devices = [("null", 1, 3), ("zero", 1, 5), ("random", 1, 8), ("urandom", 1, 9)];
+        for (name, major, minor) in devices {
+            mknod(
+                &dev_dir.join(name),
+                SFlag::S_IFCHR,
+                Mode::from_bits_truncate(0o666),
+                makedev(major, minor),
+            )
+            .expect("Failed to create device node");
+        }
+    }
+
+    /// Bind-mounts the `sandbox_exec` helper binary from the host into the overlay at
+    /// `SANDBOXED_EXEC_PATH`, so it's reachable by that fixed path once `nsenter` joins
+    /// this sandbox's mount namespace (the host path is meaningless there).
+    fn mount_sandbox_exec(merged: &Path, sandbox_exec_path: &str) {
+        let target = merged.join(SANDBOXED_EXEC_PATH.trim_start_matches('/'));
+        fs::write(&target, []).expect("Failed to create sandbox_exec mount point");
+        mount(
+            Some(sandbox_exec_path),
+            &target,
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        )
+        .expect("Failed to bind-mount sandbox_exec");
+    }
+
+    fn mount_tmp(merged: &Path) {
+        let tmp_dir = merged.join("tmp");
+        fs::create_dir_all(&tmp_dir).expect("Failed to create /tmp mount point");
+        mount(
+            Some("tmpfs"),
+            &tmp_dir,
+            Some("tmpfs"),
+            MsFlags::empty(),
+            Some("size=64m,mode=1777"),
+        )
+        .expect("Failed to mount /tmp tmpfs");
+    }
+
+    /// Swaps `merged` in as `/` via `pivot_root`, then unmounts the old root instead of
+    /// leaving it reachable the way `chroot` does.
+    fn pivot(merged: &Path) {
+        let old_root = merged.join(".old_root");
+        fs::create_dir_all(&old_root).expect("Failed to create pivot_root staging directory");
+
+        pivot_root(merged, &old_root).expect("Failed to pivot_root");
+        chdir("/").expect("Failed to chdir into new root");
+
+        umount2("/.old_root", MntFlags::MNT_DETACH).expect("Failed to unmount old root");
+        fs::remove_dir("/.old_root").ok();
+    }
+}
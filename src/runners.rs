@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+/// Describes a language by its source extension, an optional compile command template,
+/// and a run command template, so adding a language is a new entry in `REGISTRY` rather
+/// than a new copy-pasted handler. Templates use `{src}` and `{bin}` placeholders, filled
+/// in against the workdir `write_source` creates.
+#[derive(Debug, Clone, Copy)]
+pub struct LanguageRunner {
+    pub lang: &'static str,
+    pub extension: &'static str,
+    pub compile_command: Option<&'static str>,
+    pub run_command: &'static str,
+}
+
+pub static REGISTRY: &[LanguageRunner] = &[
+    LanguageRunner {
+        lang: "python",
+        extension: "py",
+        compile_command: None,
+        run_command: "python3 {src}",
+    },
+    LanguageRunner {
+        lang: "cpp",
+        extension: "cpp",
+        compile_command: Some("g++ -o {bin} {src}"),
+        run_command: "{bin}",
+    },
+];
+
+impl LanguageRunner {
+    pub fn lookup(lang: &str) -> Option<&'static LanguageRunner> {
+        REGISTRY.iter().find(|runner| runner.lang == lang)
+    }
+
+    /// Writes `code` to a fresh temp source file and, if this language compiles,
+    /// reserves the binary's path alongside it. Both paths are removed once the
+    /// returned `Workdir` is dropped.
+    pub fn write_source(&self, code: &str) -> std::io::Result<Workdir> {
+        let id = Uuid::new_v4();
+        let source_path = PathBuf::from(format!("/tmp/{}.{}", id, self.extension));
+        fs::write(&source_path, code)?;
+
+        let binary_path = self
+            .compile_command
+            .map(|_| PathBuf::from(format!("/tmp/{}", id)));
+
+        Ok(Workdir { source_path, binary_path })
+    }
+
+    pub fn compile_command(&self, workdir: &Workdir) -> Option<String> {
+        self.compile_command.map(|template| self.render(template, workdir))
+    }
+
+    pub fn run_command(&self, workdir: &Workdir) -> String {
+        self.render(self.run_command, workdir)
+    }
+
+    fn render(&self, template: &str, workdir: &Workdir) -> String {
+        let mut rendered = template.replace("{src}", &workdir.source_path.to_string_lossy());
+        if let Some(binary_path) = &workdir.binary_path {
+            rendered = rendered.replace("{bin}", &binary_path.to_string_lossy());
+        }
+        rendered
+    }
+}
+
+/// The temp files created for a single run. Cleans up both the source file and (if
+/// compiled) the binary when dropped, so every return path out of the handler — early
+/// error, timeout, or success — leaves nothing behind in `/tmp`.
+pub struct Workdir {
+    source_path: PathBuf,
+    binary_path: Option<PathBuf>,
+}
+
+impl Drop for Workdir {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.source_path);
+        if let Some(binary_path) = &self.binary_path {
+            let _ = fs::remove_file(binary_path);
+        }
+    }
+}
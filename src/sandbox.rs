@@ -1,34 +1,46 @@
 use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::thread::sleep;
 use std::time::Duration;
 
-use libc::prctl;
-use libc::PR_SET_SECCOMP;
-use libc::SECCOMP_MODE_FILTER;
-use nix::libc::{prlimit, rlimit, RLIMIT_CPU, RLIMIT_FSIZE};
+use std::os::unix::process::ExitStatusExt;
+
 use nix::sched::{unshare, CloneFlags};
 use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
-use nix::unistd::chroot;
 use nix::unistd::{fork, ForkResult, Pid};
 
-use seccomp::Compare;
-use seccomp::Context;
-use seccomp::{Action, Rule};
-
-use crate::config::constants::Settings;
+use crate::cgroup::{self, CgroupBackend};
+use crate::constants::{ResourceLimits, Settings, SANDBOXED_EXEC_PATH};
+use crate::rootfs;
+use crate::session::PtySession;
 
 pub struct Sandbox {
     pid: Pid,
+    limits: ResourceLimits,
+}
+
+/// The result of a single `run_command`: both captured streams, the real exit status
+/// (a code, or a signal if the process was killed), and how long it took end to end.
+#[derive(Debug, Clone)]
+pub struct ExecutionOutcome {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub wall_time_ms: u128,
 }
 
 impl Sandbox {
     /// Creates a new sandboxed process using `fork()`.
-    /// The child process enters isolated namespaces and applies cgroups and resource limits.
+    /// The child process enters isolated namespaces and sets up cgroups for memory and
+    /// process-count limits. `limits` is held onto so `run_command`/`run_interactive` can
+    /// pass `cpu_seconds`/`max_file_bytes` through to `sandbox_exec`, and the seccomp
+    /// filter is applied there too — this forked child just sleeps forever as the
+    /// namespace "holder"; see `attach_workload_to_cgroup` and `sandbox_exec` for why
+    /// enforcement can't live here.
     /// Returns `Some(Sandbox)` if successful, otherwise `None`.
-    pub fn new() -> Option<Self> {
+    pub fn new(limits: ResourceLimits) -> Option<Self> {
         match unsafe { fork() } {
             Ok(ForkResult::Child) => {
                 unshare(
@@ -46,26 +58,23 @@ impl Sandbox {
                 println!("Child process with PID: {}", pid);
 
                 if (Settings::from_env().use_complete_isolation) {
-                    Self::configure_cgroups(pid);
+                    let cgroup = cgroup::detect_backend(pid);
+                    Self::configure_cgroups(cgroup.as_ref(), &limits, pid);
                     Self::isolate_filesystem(pid);
                     Self::drop_root_privileges();
-                    Self::apply_seccomp(pid);
-                    Self::limit_process_count(pid);
-                    Self::disable_network(pid);
+                    Self::limit_process_count(cgroup.as_ref(), &limits);
+                    Self::disable_network(cgroup.as_ref());
                 } else {
                     eprintln!("\x1b[33mWarning: Not using complete isolation setup!\x1b[0m");
                 }
 
-                Self::set_process_limit(pid, RLIMIT_CPU, 10);
-                Self::set_process_limit(pid, RLIMIT_FSIZE, 20 * 1024 * 1024);
-
                 loop {
                     sleep(Duration::from_secs(1));
                 }
             }
             Ok(ForkResult::Parent { child }) => {
                 println!("Parent create child with PID: {}", child);
-                Some(Sandbox { pid: child })
+                Some(Sandbox { pid: child, limits })
             }
             Err(_) => {
                 eprintln!("Failed to fork process");
@@ -82,117 +91,34 @@ impl Sandbox {
         fs::write("/proc/self/gid_map", "1000 1000 1").expect("Failed to set UID map");
     }
 
-    /// Isolates the filesystem by using `chroot` to set the root directory for the process.
-    /// The process will only be able to access files within this new root directory.
+    /// Isolates the filesystem by building a real root (read-only base rootfs plus a
+    /// writable overlay, with fresh `/proc`, `/dev`, and `/tmp`) and `pivot_root`-ing
+    /// into it. See `rootfs::SandboxRoot` for why this replaced `chroot`.
     fn isolate_filesystem(pid: Pid) {
-        let root_dir = format!("sandbox/{}/root", pid);
-        chroot(root_dir.as_str()).expect("Failed to chroot");
-        std::env::set_current_dir("/").expect("Failed to change directory.");
+        let settings = Settings::from_env();
+        rootfs::SandboxRoot::enter(pid.as_raw(), &settings.base_rootfs_path, &settings.sandbox_exec_path);
     }
 
     /// Limits the process count for the sandboxed process by configuring the cgroup for PIDs.
     /// This prevents the process from creating an excessive number of child processes.
-    fn limit_process_count(pid: Pid) {
-        let cgroup_path = format!("/sys/fs/cgroup/sandbox_{}/pids.max", pid);
-        if !Path::new(&cgroup_path).exists() {
-            fs::create_dir_all(&cgroup_path).expect("Failed to create cgroup directory");
-        }
-
-        fs::write(cgroup_path, "20").expect("Failed to set process limit");
+    fn limit_process_count(cgroup: &dyn CgroupBackend, limits: &ResourceLimits) {
+        cgroup.set_pids_max(limits.max_processes);
     }
 
-    /// Disables network access for the sandboxed process by configuring the cgroup to block networking.
+    /// Disables network access for the sandboxed process via the cgroup backend.
     /// This ensures the process cannot access the internet or other network resources.
-    fn disable_network(pid: Pid) {
-        let net_cls = format!("/sys/fs/cgroup/sandbox_{}/net_cls.classid", pid);
-        fs::write(net_cls, "0").expect("Failed to disable network access");
+    fn disable_network(cgroup: &dyn CgroupBackend) {
+        cgroup.disable_network();
     }
 
-    /// Applies a seccomp filter to restrict the system calls that the sandboxed process can make.
-    /// This is done to prevent the process from performing harmful or dangerous operations.
-    fn apply_seccomp(pid: Pid) {
-        let mut ctx =
-            Context::default(Action::Kill).expect("Error occurred while setting context.");
-
-        let read_rule = Rule::new(
-            0,
-            Compare::arg(0)
-                .using(seccomp::Op::Ge)
-                .with(0)
-                .build()
-                .unwrap(),
-            Action::Allow,
-        );
-        ctx.add_rule(read_rule).expect("Failed to set read rule.");
-
-        let write_rule = Rule::new(
-            1,
-            Compare::arg(0)
-                .using(seccomp::Op::Ge)
-                .with(0)
-                .build()
-                .unwrap(),
-            Action::Allow,
-        );
-        ctx.add_rule(write_rule).expect("Failed to set write rule.");
-
-        let exit_rule = Rule::new(
-            60,
-            Compare::arg(0)
-                .using(seccomp::Op::Ge)
-                .with(0)
-                .build()
-                .unwrap(),
-            Action::Allow,
-        );
-        ctx.add_rule(exit_rule).expect("Failed to set exit rule.");
-
-        ctx.load().expect("Failed to load context");
-
-        unsafe {
-            let res = prctl(
-                PR_SET_SECCOMP,
-                SECCOMP_MODE_FILTER,
-                pid.as_raw() as u32,
-                0,
-                0,
-            );
-            if res != 0 {
-                eprintln!("Failed to apply seccomp filter to PID {}", pid);
-            }
-        }
-    }
-
-    /// Configures cgroups for the given process ID (`pid`).
-    /// CPU and memory limits are applied if `ENABLE_CGROUPS=true` is set in the environment.
-    fn configure_cgroups(pid: Pid) {
-        let cgroup_path = format!("/sys/fs/cgroup/sandbox_{}", pid);
-        fs::create_dir_all(&cgroup_path).expect("Failed to create cgroup directory");
-
-        fs::write(format!("{}/cpu.max", cgroup_path), "50000 100000")
-            .expect("Failed to set CPU limit");
-        fs::write(format!("{}/memory.max", cgroup_path), "134217728")
-            .expect("Failed to set memory limit");
-        fs::write(format!("{}/cgroup.procs", cgroup_path), pid.to_string())
-            .expect("Failed to add process to cgroup");
-    }
-
-    /// Sets resource limits (e.g., CPU time, file size) for a process.
-    fn set_process_limit(pid: Pid, resource: u32, limit: u64) {
-        let rlim = rlimit {
-            rlim_cur: limit,
-            rlim_max: limit,
-        };
-
-        let ret = unsafe { prlimit(pid.as_raw(), resource, &rlim, std::ptr::null_mut()) };
-
-        if ret != 0 {
-            eprintln!(
-                "Failed to set rlimit for PID: {}: {}",
-                pid,
-                std::io::Error::last_os_error()
-            )
-        }
+    /// Configures cgroups for the given process ID (`pid`), using whichever backend
+    /// (`cgroup`) was selected for the host's hierarchy. Attaches `pid` itself (the
+    /// holder) so the cgroup directory isn't empty, but the holder never does any real
+    /// work — see `attach_workload_to_cgroup` for where the actual workload joins.
+    fn configure_cgroups(cgroup: &dyn CgroupBackend, limits: &ResourceLimits, pid: Pid) {
+        cgroup.set_cpu_quota(50000, 100000);
+        cgroup.set_memory_max(limits.memory_bytes);
+        cgroup.attach_pid(pid);
     }
 
     /// Checks if the sandboxed process is still running.
@@ -205,27 +131,110 @@ impl Sandbox {
         }
     }
 
-    /// Runs a command inside the sandboxed process using `nsenter`.
-    pub fn run_command(&self, cmd: &str) -> Result<String, String> {
-        let command = format!("nsenter --target {} --pid -- sh -c \"{}\"", self.pid, cmd);
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .output()
-            .map_err(|e| format!("{}", e))?;
+    /// Builds the `nsenter` argument list that joins this sandbox's namespaces and execs
+    /// `sandbox_exec` (bind-mounted into the sandbox's root by `rootfs::SandboxRoot`, at
+    /// `SANDBOXED_EXEC_PATH`) directly — no outer `sh -c "nsenter ..."` wrapper, and
+    /// `--no-fork` so `nsenter` execs the target in place rather than forking a child of
+    /// its own. Both matter for the same reason: they're what let
+    /// `run_command`/`run_interactive` read back the *real* workload's pid from
+    /// `Child::id()`/`PtySession::spawn`, instead of the pid of an intermediate shell or
+    /// fork nsenter would otherwise introduce. `sandbox_exec` is what then installs
+    /// `seccomp_profile`'s filter and `limits`' `RLIMIT_CPU`/`RLIMIT_FSIZE` on itself
+    /// before exec'ing `cmd` in turn, since that's the only place either can actually land
+    /// on the real workload.
+    fn nsenter_args(pid: Pid, seccomp_profile: &str, limits: &ResourceLimits, cmd: &str) -> Vec<String> {
+        vec![
+            "--target".to_string(),
+            pid.to_string(),
+            "--mount".to_string(),
+            "--uts".to_string(),
+            "--ipc".to_string(),
+            "--net".to_string(),
+            "--pid".to_string(),
+            "--user".to_string(),
+            "--no-fork".to_string(),
+            "--".to_string(),
+            SANDBOXED_EXEC_PATH.to_string(),
+            seccomp_profile.to_string(),
+            limits.cpu_seconds.to_string(),
+            limits.max_file_bytes.to_string(),
+            cmd.to_string(),
+        ]
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-        } else {
-            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    /// Moves the real, just-spawned workload pid into this sandbox's cgroup. `nsenter`
+    /// only shares namespace *views* with the holder created in `new` (via `setns`);
+    /// cgroup membership isn't namespace-scoped, so without this the cgroup limits
+    /// configured in `new` only ever constrain the holder, which does nothing but sleep.
+    fn attach_workload_to_cgroup(&self, real_pid: u32) {
+        if !Settings::from_env().use_complete_isolation {
+            return;
         }
+        cgroup::detect_backend(self.pid).attach_pid(Pid::from_raw(real_pid as i32));
+    }
+
+    /// Runs a command inside the sandboxed process using `nsenter`, capturing stdout and
+    /// stderr separately along with the real exit status. `seccomp_profile` selects the
+    /// syscall allowlist `sandbox_exec` installs on itself before exec'ing `cmd` (see
+    /// `SeccompPolicy::named`), or is overridden by `Settings::seccomp_policy_path` if one
+    /// is configured; `sandbox_exec` also applies `self.limits`' `RLIMIT_CPU`/
+    /// `RLIMIT_FSIZE` to itself there, for the same reason. Only fails if the `nsenter`
+    /// process itself couldn't be spawned; a nonzero exit or a signal is reported through
+    /// `ExecutionOutcome`, not as an `Err`.
+    pub fn run_command(&self, seccomp_profile: &str, cmd: &str) -> Result<ExecutionOutcome, String> {
+        let start = std::time::Instant::now();
+
+        let mut child = Command::new("nsenter")
+            .args(Self::nsenter_args(self.pid, seccomp_profile, &self.limits, cmd))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}", e))?;
+
+        self.attach_workload_to_cgroup(child.id());
+
+        let output = child.wait_with_output().map_err(|e| format!("{}", e))?;
+
+        Ok(ExecutionOutcome {
+            stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            exit_code: output.status.code(),
+            signal: output.status.signal(),
+            wall_time_ms: start.elapsed().as_millis(),
+        })
+    }
+
+    /// Reads how much CPU time this sandbox's cgroup has accounted against it so far.
+    /// `None` if the host's cgroup hierarchy doesn't expose that accounting (see
+    /// `cgroup::read_cpu_time_ms`).
+    pub fn cpu_time_ms(&self) -> Option<u64> {
+        cgroup::read_cpu_time_ms(self.pid)
     }
 
-    /// Terminates the sandboxed process gracefully using `SIGTERM`.
-    /// Waits up to 5 seconds for the process to exit.
+    /// Runs a command inside the sandboxed process with a PTY as its stdin/stdout (not
+    /// its controlling terminal), for interactive sessions (REPLs, programs that read stdin, long-running
+    /// output). Returns the session (for forwarding bytes to/from a WebSocket) and the
+    /// `nsenter` child process. See `run_command` for what `seccomp_profile` selects.
+    pub fn run_interactive(
+        &self,
+        seccomp_profile: &str,
+        cmd: &str,
+    ) -> std::io::Result<(PtySession, std::process::Child)> {
+        let (session, child) = PtySession::spawn(
+            "nsenter",
+            &Self::nsenter_args(self.pid, seccomp_profile, &self.limits, cmd),
+        )?;
+        self.attach_workload_to_cgroup(child.id());
+        Ok((session, child))
+    }
+
+    /// Terminates the sandboxed process gracefully using `SIGTERM`, waiting up to 5
+    /// seconds for it to exit, then cleans up the per-sandbox overlay directories and
+    /// mounts `isolate_filesystem` set up for it (see `rootfs::SandboxRoot::cleanup`).
     pub fn terminate(&self) {
         if let Err(e) = kill(self.pid, Signal::SIGTERM) {
             eprintln!("Failed to kill process: {}", e);
+            rootfs::SandboxRoot::cleanup(self.pid.as_raw());
             return;
         }
 
@@ -233,11 +242,13 @@ impl Sandbox {
             match waitpid(self.pid, Some(WaitPidFlag::WNOHANG)) {
                 Ok(WaitStatus::Exited(_, _)) | Ok(WaitStatus::Signaled(_, _, _)) => {
                     println!("Process {} exited gracefully", self.pid);
-                    return;
+                    break;
                 }
                 Ok(_) | Err(_) => {}
             }
             sleep(Duration::from_secs(1));
         }
+
+        rootfs::SandboxRoot::cleanup(self.pid.as_raw());
     }
 }
@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::Path;
+
+use seccomp::{Action, Compare, Context, Rule};
+
+/// One syscall/action pair resolved to the syscall's numeric identifier so it can be
+/// handed straight to a `seccomp::Rule`.
+#[derive(Debug, Clone, Copy)]
+pub struct SeccompRule {
+    pub syscall_nr: i64,
+    pub action: Action,
+}
+
+/// A declarative seccomp profile: the action taken for any syscall not explicitly
+/// listed, plus the explicit allow/kill/trap rules layered on top of it.
+#[derive(Debug, Clone)]
+pub struct SeccompPolicy {
+    pub default_action: Action,
+    pub rules: Vec<SeccompRule>,
+}
+
+impl SeccompPolicy {
+    /// Resolves a policy by name, falling back to the built-in "strict" profile for
+    /// anything unrecognized.
+    pub fn named(name: &str) -> Self {
+        match name {
+            "interpreter" => Self::interpreter(),
+            "compiler" => Self::compiler(),
+            _ => Self::strict(),
+        }
+    }
+
+    /// Loads a policy from a declarative profile file, one `syscall: action` pair per
+    /// line (`default: <action>` sets the default action). Lines starting with `#` and
+    /// blank lines are ignored.
+    pub fn from_file(path: &Path) -> Self {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read seccomp policy {}: {}", path.display(), e));
+        Self::parse(&contents)
+    }
+
+    /// The minimal profile: only `read`, `write`, and `exit`/`exit_group` are allowed,
+    /// everything else is killed. Suitable for trivial, non-interpreter workloads.
+    pub fn strict() -> Self {
+        Self {
+            default_action: Action::Kill,
+            rules: allow_rules(&["read", "write", "exit", "exit_group"]),
+        }
+    }
+
+    /// A larger-but-still-restricted profile covering what a real `python3` or compiled
+    /// C++ binary needs to start up, allocate memory, read its source/binary, and exit.
+    pub fn interpreter() -> Self {
+        Self {
+            default_action: Action::Kill,
+            rules: allow_rules(INTERPRETER_SYSCALLS),
+        }
+    }
+
+    /// `interpreter`'s allowlist plus the syscalls `g++` itself needs while compiling
+    /// (writing object files, renaming them into place, walking the include path). Used
+    /// for the compile step of languages that have one; `interpreter` alone is enough to
+    /// just run the resulting binary.
+    pub fn compiler() -> Self {
+        Self {
+            default_action: Action::Kill,
+            rules: allow_rules(
+                &INTERPRETER_SYSCALLS
+                    .iter()
+                    .copied()
+                    .chain(COMPILER_EXTRA_SYSCALLS.iter().copied())
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// Installs this policy's seccomp-bpf filter on the calling thread via
+    /// `Context::load()`. Filters only ever apply to the thread that installs them (and
+    /// whatever it later execs into), not to other processes or to namespace-joiners, so
+    /// this must run in the process about to exec the sandboxed workload itself —
+    /// see `sandbox_exec`, the only caller.
+    pub fn install(&self) {
+        let mut ctx = Context::default(self.default_action)
+            .expect("Error occurred while setting context.");
+
+        for rule in &self.rules {
+            let compare = Compare::arg(0)
+                .using(seccomp::Op::Ge)
+                .with(0)
+                .build()
+                .unwrap();
+            ctx.add_rule(Rule::new(rule.syscall_nr as usize, compare, rule.action))
+                .expect("Failed to add seccomp rule.");
+        }
+
+        ctx.load().expect("Failed to load seccomp context");
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut default_action = Action::Kill;
+        let mut rules = Vec::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let action = parse_action(value.trim());
+
+            if key == "default" {
+                default_action = action;
+                continue;
+            }
+
+            match syscall_number(key) {
+                Some(syscall_nr) => rules.push(SeccompRule { syscall_nr, action }),
+                None => eprintln!(
+                    "\x1b[33mWarning: unknown syscall '{}' in seccomp policy, skipping\x1b[0m",
+                    key
+                ),
+            }
+        }
+
+        Self { default_action, rules }
+    }
+}
+
+/// What a real `python3` or compiled C++ binary needs to start up, allocate memory, read
+/// its source/binary, and exit.
+const INTERPRETER_SYSCALLS: &[&str] = &[
+    "read",
+    "write",
+    "exit",
+    "exit_group",
+    "mmap",
+    "munmap",
+    "mprotect",
+    "madvise",
+    "brk",
+    "openat",
+    "open",
+    "close",
+    "fstat",
+    "stat",
+    "lstat",
+    "access",
+    "readlink",
+    "execve",
+    "arch_prctl",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "sigaltstack",
+    "getrandom",
+    "set_tid_address",
+    "set_robust_list",
+    "futex",
+    "clone",
+    "wait4",
+    "dup2",
+    "fcntl",
+    "ioctl",
+    "pipe2",
+    "getpid",
+    "getppid",
+    "getcwd",
+    "chdir",
+    "nanosleep",
+    "sched_yield",
+    "prlimit64",
+    "kill",
+    "tgkill",
+];
+
+/// What `g++` additionally needs on top of `INTERPRETER_SYSCALLS` while compiling: writing
+/// object files, renaming them into place, creating temp directories for intermediate
+/// output, and walking the include path.
+const COMPILER_EXTRA_SYSCALLS: &[&str] = &[
+    "unlink",
+    "unlinkat",
+    "rename",
+    "renameat",
+    "mkdir",
+    "statx",
+    "lseek",
+    "pread64",
+    "pwrite64",
+    "clone3",
+    "getdents64",
+];
+
+fn allow_rules(names: &[&str]) -> Vec<SeccompRule> {
+    names
+        .iter()
+        .filter_map(|name| {
+            syscall_number(name).map(|syscall_nr| SeccompRule {
+                syscall_nr,
+                action: Action::Allow,
+            })
+        })
+        .collect()
+}
+
+fn parse_action(value: &str) -> Action {
+    match value {
+        "allow" => Action::Allow,
+        "trap" => Action::Trap,
+        _ => Action::Kill,
+    }
+}
+
+/// Resolves a syscall name to its Linux syscall number. Only the syscalls referenced by
+/// the built-in profiles are listed; extend this table as new profiles need more.
+fn syscall_number(name: &str) -> Option<i64> {
+    let table: &[(&str, i64)] = &[
+        ("read", libc::SYS_read),
+        ("write", libc::SYS_write),
+        ("exit", libc::SYS_exit),
+        ("exit_group", libc::SYS_exit_group),
+        ("mmap", libc::SYS_mmap),
+        ("munmap", libc::SYS_munmap),
+        ("mprotect", libc::SYS_mprotect),
+        ("madvise", libc::SYS_madvise),
+        ("brk", libc::SYS_brk),
+        ("openat", libc::SYS_openat),
+        ("open", libc::SYS_open),
+        ("close", libc::SYS_close),
+        ("fstat", libc::SYS_fstat),
+        ("stat", libc::SYS_stat),
+        ("lstat", libc::SYS_lstat),
+        ("access", libc::SYS_access),
+        ("readlink", libc::SYS_readlink),
+        ("execve", libc::SYS_execve),
+        ("arch_prctl", libc::SYS_arch_prctl),
+        ("rt_sigaction", libc::SYS_rt_sigaction),
+        ("rt_sigprocmask", libc::SYS_rt_sigprocmask),
+        ("rt_sigreturn", libc::SYS_rt_sigreturn),
+        ("sigaltstack", libc::SYS_sigaltstack),
+        ("getrandom", libc::SYS_getrandom),
+        ("set_tid_address", libc::SYS_set_tid_address),
+        ("set_robust_list", libc::SYS_set_robust_list),
+        ("futex", libc::SYS_futex),
+        ("clone", libc::SYS_clone),
+        ("wait4", libc::SYS_wait4),
+        ("dup2", libc::SYS_dup2),
+        ("fcntl", libc::SYS_fcntl),
+        ("ioctl", libc::SYS_ioctl),
+        ("pipe2", libc::SYS_pipe2),
+        ("getpid", libc::SYS_getpid),
+        ("getppid", libc::SYS_getppid),
+        ("getcwd", libc::SYS_getcwd),
+        ("chdir", libc::SYS_chdir),
+        ("nanosleep", libc::SYS_nanosleep),
+        ("sched_yield", libc::SYS_sched_yield),
+        ("prlimit64", libc::SYS_prlimit64),
+        ("kill", libc::SYS_kill),
+        ("tgkill", libc::SYS_tgkill),
+        ("unlink", libc::SYS_unlink),
+        ("unlinkat", libc::SYS_unlinkat),
+        ("rename", libc::SYS_rename),
+        ("renameat", libc::SYS_renameat),
+        ("mkdir", libc::SYS_mkdir),
+        ("statx", libc::SYS_statx),
+        ("lseek", libc::SYS_lseek),
+        ("pread64", libc::SYS_pread64),
+        ("pwrite64", libc::SYS_pwrite64),
+        ("clone3", libc::SYS_clone3),
+        ("getdents64", libc::SYS_getdents64),
+    ];
+
+    table
+        .iter()
+        .find(|(syscall_name, _)| *syscall_name == name)
+        .map(|(_, syscall_nr)| *syscall_nr)
+}
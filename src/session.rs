@@ -0,0 +1,103 @@
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::process::{Child, Command, Stdio};
+
+use nix::pty::openpty;
+use nix::unistd::{dup, pipe, read, write};
+use serde::{Deserialize, Serialize};
+
+/// How one of a sandboxed process's standard streams is wired up.
+/// Only `Null` changes behaviour; `Inherit` is accepted over HTTP/WS and handled the
+/// same as `Piped`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoDisposition {
+    /// Handled the same as `Piped`; no stream is inherited from the sandbox host process.
+    Inherit,
+    /// Connected to a pipe (or, for sessions, the PTY/stderr pipe) the caller reads/writes
+    /// explicitly.
+    #[default]
+    Piped,
+    /// Not used by the caller: a batch run reads stdin from `/dev/null`, a session forwards
+    /// no input into stdin, and a session's stdout/stderr set to `Null` is neither read nor
+    /// forwarded. The stream itself is not closed, and a batch run captures stdout/stderr
+    /// regardless.
+    Null,
+}
+
+/// A PTY-backed sandbox session. The server holds the master half and forwards bytes
+/// to/from a WebSocket; the slave half is the sandboxed child's stdin/stdout (it is not
+/// made the child's controlling terminal). stderr is deliberately *not* wired to the slave: a PTY has
+/// only one stream, so routing stderr through it would merge the two regardless of the
+/// caller's `stdout`/`stderr` dispositions. Instead stderr is captured on its own pipe so
+/// the two can be forwarded (or suppressed) independently.
+pub struct PtySession {
+    master: OwnedFd,
+    stderr_read: OwnedFd,
+}
+
+impl PtySession {
+    /// Allocates a PTY pair and a plain pipe for stderr.
+    fn open() -> nix::Result<(Self, OwnedFd, OwnedFd)> {
+        let pty = openpty(None, None)?;
+        let (stderr_read, stderr_write) = pipe()?;
+        Ok((
+            Self {
+                master: pty.master,
+                stderr_read,
+            },
+            pty.slave,
+            stderr_write,
+        ))
+    }
+
+    /// Allocates a PTY and spawns `program`/`args` inside it, with stdin/stdout attached
+    /// to the PTY slave and stderr attached to a separate pipe. The slave side and the
+    /// pipe's write end are closed in this process once the child has them, so only the
+    /// child holds them open. Takes an already-split `program`/`args` (rather than a
+    /// single shell string) so callers that need the real spawned pid — e.g. to attach it
+    /// to a cgroup — aren't left guessing which process behind an extra `sh -c` layer it
+    /// refers to.
+    pub fn spawn(program: &str, args: &[String]) -> std::io::Result<(Self, Child)> {
+        let (session, slave, stderr_write) = Self::open().map_err(std::io::Error::from)?;
+
+        let slave_stdin = dup_owned_fd(&slave)?;
+        let slave_stdout = dup_owned_fd(&slave)?;
+
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::from(slave_stdin))
+            .stdout(Stdio::from(slave_stdout))
+            .stderr(Stdio::from(stderr_write))
+            .spawn()?;
+
+        Ok((session, child))
+    }
+
+    pub fn master_fd(&self) -> RawFd {
+        self.master.as_raw_fd()
+    }
+
+    /// Forwards client bytes into the PTY master, i.e. to the child's stdin.
+    /// Always writes; the session does not know the stdin disposition, so callers check
+    /// for `Null` before forwarding WebSocket input.
+    pub fn write_input(&self, bytes: &[u8]) -> nix::Result<usize> {
+        write(&self.master, bytes)
+    }
+
+    /// Reads whatever the child has written to stdout since the last call. Blocks until
+    /// at least one byte is available or the slave side has been closed (`Ok(0)`).
+    pub fn read_output(&self, buf: &mut [u8]) -> nix::Result<usize> {
+        read(self.master.as_raw_fd(), buf)
+    }
+
+    /// Reads whatever the child has written to stderr since the last call. Blocks until
+    /// at least one byte is available or the pipe's write end has been closed (`Ok(0)`).
+    pub fn read_stderr(&self, buf: &mut [u8]) -> nix::Result<usize> {
+        read(self.stderr_read.as_raw_fd(), buf)
+    }
+}
+
+fn dup_owned_fd(fd: &OwnedFd) -> std::io::Result<OwnedFd> {
+    let raw = dup(fd.as_raw_fd()).map_err(std::io::Error::from)?;
+    Ok(unsafe { OwnedFd::from_raw_fd(raw) })
+}